@@ -1,4 +1,4 @@
-use super::utils::logitems::ItemBatch;
+use super::utils::logitems::{ItemBatch, LogEntry};
 use super::visibility_blocking;
 use crate::{
 	components::{
@@ -13,20 +13,32 @@ use crate::{
 use anyhow::Result;
 use asyncgit::{
 	sync::{
-		diff::DiffOptions, diff_contains_file, get_commits_info,
-		CommitId, RepoPathRef,
+		diff::{DiffLineType, DiffOptions, FileDiff},
+		diff_contains_file, get_commits_info,
+		repository::repo as open_repo, CommitId, RepoPath, RepoPathRef,
 	},
 	AsyncDiff, AsyncGitNotification, AsyncLog, DiffParams, DiffType,
 	FetchStatus,
 };
 use chrono::{DateTime, Local};
 use crossbeam_channel::Sender;
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use git2::DiffOptions as Git2DiffOptions;
+use image::{imageops::FilterType, GenericImageView};
+use once_cell::sync::Lazy;
+use syntect::{
+	easy::HighlightLines,
+	highlighting::{Style as SyntectStyle, Theme, ThemeSet},
+	parsing::SyntaxSet,
+};
 use tui::{
 	backend::Backend,
 	layout::{Constraint, Direction, Layout, Rect},
+	style::{Color, Modifier, Style},
 	text::{Span, Spans, Text},
-	widgets::{Block, Borders, Cell, Clear, Row, Table, TableState},
+	widgets::{
+		Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState,
+	},
 	Frame,
 };
 
@@ -49,6 +61,52 @@ pub struct FileRevlogComponent {
 	key_config: SharedKeyConfig,
 	current_width: std::cell::Cell<usize>,
 	current_height: std::cell::Cell<usize>,
+	filter: Option<Filter>,
+	follow_renames: bool,
+	path_history: std::sync::Arc<std::sync::Mutex<Vec<PathSegment>>>,
+	renames_resolved_until: usize,
+	renames_pending: std::sync::Arc<std::sync::atomic::AtomicBool>,
+	renames_epoch: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+	image_previews: std::cell::RefCell<
+		std::collections::HashMap<CommitId, ImagePreview>,
+	>,
+	current_diff: Option<(String, FileDiff)>,
+}
+
+/// a decoded image blob, downscaled to fit the diff pane on demand and
+/// cached by commit id so re-selecting a revision (e.g. scrolling) does
+/// not re-decode or re-scale it
+struct ImagePreview {
+	image: image::DynamicImage,
+	last_render:
+		std::cell::RefCell<Option<(u32, u32, Vec<Spans<'static>>)>>,
+}
+
+/// a historical path the tracked file was known by, starting at
+/// `start` (an index into the loaded commits, 0 = newest) and
+/// continuing for all older commits until the next segment's `start`
+struct PathSegment {
+	start: usize,
+	path: String,
+}
+
+/// incremental fuzzy-filter state for the revlog table
+#[derive(Default)]
+struct Filter {
+	query: String,
+	editing: bool,
+	matches: Vec<FilterMatch>,
+}
+
+/// a single commit row that matched the active filter query, along with
+/// the matched character positions per field so `get_rows` can highlight
+/// them
+struct FilterMatch {
+	item_index: usize,
+	score: i64,
+	hash_indices: Vec<usize>,
+	author_indices: Vec<usize>,
+	msg_indices: Vec<usize>,
 }
 
 impl FileRevlogComponent {
@@ -85,6 +143,22 @@ impl FileRevlogComponent {
 			key_config,
 			current_width: std::cell::Cell::new(0),
 			current_height: std::cell::Cell::new(0),
+			filter: None,
+			follow_renames: false,
+			path_history: std::sync::Arc::new(std::sync::Mutex::new(
+				Vec::new(),
+			)),
+			renames_resolved_until: 0,
+			renames_pending: std::sync::Arc::new(
+				std::sync::atomic::AtomicBool::new(false),
+			),
+			renames_epoch: std::sync::Arc::new(
+				std::sync::atomic::AtomicUsize::new(0),
+			),
+			image_previews: std::cell::RefCell::new(
+				std::collections::HashMap::new(),
+			),
+			current_diff: None,
 		}
 	}
 
@@ -95,6 +169,21 @@ impl FileRevlogComponent {
 	///
 	pub fn open(&mut self, file_path: &str) -> Result<()> {
 		self.file_path = Some(file_path.into());
+		self.filter = None;
+		// invalidate any `resolve_renames` thread still running for the
+		// previously opened file, so it cannot extend this file's fresh
+		// `path_history` with stale segments once it completes
+		self.renames_epoch
+			.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+		if let Ok(mut history) = self.path_history.lock() {
+			*history =
+				vec![PathSegment { start: 0, path: file_path.into() }];
+		}
+		self.renames_resolved_until = 0;
+		self.renames_pending
+			.store(false, std::sync::atomic::Ordering::SeqCst);
+		self.image_previews.borrow_mut().clear();
+		self.current_diff = None;
 
 		let filter = diff_contains_file(
 			self.repo_path.borrow().clone(),
@@ -129,9 +218,7 @@ impl FileRevlogComponent {
 			let log_changed =
 				git_log.fetch()? == FetchStatus::Started;
 
-			let table_state = self.table_state.take();
-			let start = table_state.selected().unwrap_or(0);
-			self.table_state.set(table_state);
+			let start = self.load_start();
 
 			if self.items.needs_data(start, git_log.count()?)
 				|| log_changed
@@ -164,11 +251,34 @@ impl FileRevlogComponent {
 
 	pub fn update_diff(&mut self) -> Result<()> {
 		if self.is_visible() {
-			if let Some(commit_id) = self.selected_commit() {
-				if let Some(file_path) = &self.file_path {
+			if let Some(item_index) = self.selected_item_index() {
+				if let Some(entry) = self.items.iter().nth(item_index)
+				{
+					let path =
+						self.path_for_index(item_index).to_string();
+
+					if is_image_path(&path) {
+						self.update_image_preview(entry.id, &path)?;
+
+						if self
+							.image_previews
+							.borrow()
+							.contains_key(&entry.id)
+						{
+							self.diff.clear(false);
+							self.current_diff = None;
+
+							return Ok(());
+						}
+
+						// not a decodable image after all (bad magic
+						// bytes or unsupported encoding) - fall back to
+						// the regular diff below
+					}
+
 					let diff_params = DiffParams {
-						path: file_path.clone(),
-						diff_type: DiffType::Commit(commit_id),
+						path: path.clone(),
+						diff_type: DiffType::Commit(entry.id),
 						options: DiffOptions::default(),
 					};
 
@@ -176,16 +286,15 @@ impl FileRevlogComponent {
 						self.git_diff.last()?
 					{
 						if params == diff_params {
-							self.diff.update(
-								file_path.to_string(),
-								false,
-								last,
-							);
+							self.current_diff =
+								Some((path.clone(), last.clone()));
+							self.diff.update(path, false, last);
 
 							return Ok(());
 						}
 					}
 
+					self.current_diff = None;
 					self.git_diff.request(diff_params)?;
 					self.diff.clear(true);
 
@@ -193,17 +302,38 @@ impl FileRevlogComponent {
 				}
 			}
 
+			self.current_diff = None;
 			self.diff.clear(false);
 		}
 
 		Ok(())
 	}
 
+	/// the commit-history offset the next slice fetch should start
+	/// from. While browsing unfiltered, this follows the table's row
+	/// selection as before. While a filter query is active, though,
+	/// `table_state.selected()` is a row index into the *matches*
+	/// list, not a commit offset, so using it here would slide the
+	/// loaded window underneath the filter every time the match
+	/// cursor moves and silently drop already-matching commits;
+	/// pin it to the top of history instead, bounding filtering to
+	/// the loaded window rather than letting it chase the cursor
+	fn load_start(&self) -> usize {
+		match &self.filter {
+			Some(filter) if !filter.query.is_empty() => 0,
+			_ => {
+				let table_state = self.table_state.take();
+				let start = table_state.selected().unwrap_or(0);
+				self.table_state.set(table_state);
+
+				start
+			}
+		}
+	}
+
 	fn fetch_commits(&mut self) -> Result<()> {
 		if let Some(git_log) = &mut self.git_log {
-			let table_state = self.table_state.take();
-
-			let start = table_state.selected().unwrap_or(0);
+			let start = self.load_start();
 
 			let commits = get_commits_info(
 				&self.repo_path.borrow(),
@@ -215,27 +345,294 @@ impl FileRevlogComponent {
 				self.items.set_items(start, commits);
 			}
 
-			self.table_state.set(table_state);
 			self.count_total = git_log.count()?;
 		}
 
+		self.resolve_renames();
+		self.apply_filter();
+
 		Ok(())
 	}
 
-	fn selected_commit(&self) -> Option<CommitId> {
+	/// kicks off a background walk of any newly-loaded commits (from
+	/// `renames_resolved_until` onward) looking for the point where the
+	/// currently tracked path appears as the new side of a rename,
+	/// pinning the old path to `path_history` for all older commits.
+	///
+	/// the walk runs on its own thread (mirroring how `AsyncLog`/
+	/// `AsyncDiff` keep git work off the UI thread for this component)
+	/// and wakes the UI up through `queue` once it has new segments.
+	fn resolve_renames(&mut self) {
+		if !self.follow_renames {
+			return;
+		}
+
+		if self
+			.renames_pending
+			.swap(true, std::sync::atomic::Ordering::SeqCst)
+		{
+			return;
+		}
+
+		let current_path = self
+			.path_history
+			.lock()
+			.ok()
+			.and_then(|history| {
+				history.last().map(|segment| segment.path.clone())
+			})
+			.unwrap_or_default();
+
+		if current_path.is_empty() {
+			self.renames_pending
+				.store(false, std::sync::atomic::Ordering::SeqCst);
+			return;
+		}
+
+		let commit_ids: Vec<CommitId> = self
+			.items
+			.iter()
+			.skip(self.renames_resolved_until)
+			.map(|entry| entry.id)
+			.collect();
+
+		if commit_ids.is_empty() {
+			self.renames_pending
+				.store(false, std::sync::atomic::Ordering::SeqCst);
+			return;
+		}
+
+		let start_index = self.renames_resolved_until;
+		self.renames_resolved_until += commit_ids.len();
+
+		let repo_path = self.repo_path.borrow().clone();
+		let path_history = self.path_history.clone();
+		let renames_pending = self.renames_pending.clone();
+		let renames_epoch = self.renames_epoch.clone();
+		let epoch = renames_epoch
+			.load(std::sync::atomic::Ordering::SeqCst);
+		let queue = self.queue.clone();
+
+		std::thread::spawn(move || {
+			let mut path = current_path;
+			let mut segments = Vec::new();
+
+			for (offset, commit_id) in
+				commit_ids.into_iter().enumerate()
+			{
+				if let Ok(Some(old_path)) =
+					find_rename_source(&repo_path, commit_id, &path)
+				{
+					if old_path != path {
+						segments.push(PathSegment {
+							start: start_index + offset + 1,
+							path: old_path.clone(),
+						});
+						path = old_path;
+					}
+				}
+			}
+
+			// `open`/`toggle_follow_renames` bump the epoch whenever
+			// they reset `path_history` out from under an in-flight
+			// walk; drop this result rather than extend a history it
+			// no longer belongs to
+			if epoch
+				== renames_epoch
+					.load(std::sync::atomic::Ordering::SeqCst)
+			{
+				if let Ok(mut history) = path_history.lock() {
+					history.extend(segments);
+				}
+
+				queue.push(InternalEvent::Update(NeedsUpdate::DIFF));
+			}
+
+			renames_pending
+				.store(false, std::sync::atomic::Ordering::SeqCst);
+		});
+	}
+
+	/// the path the tracked file was known by at the given loaded
+	/// commit index, following any renames resolved so far
+	fn path_for_index(&self, index: usize) -> String {
+		self.path_history
+			.lock()
+			.ok()
+			.and_then(|history| {
+				history
+					.iter()
+					.rev()
+					.find(|segment| segment.start <= index)
+					.map(|segment| segment.path.clone())
+			})
+			.unwrap_or_else(|| {
+				self.file_path.clone().unwrap_or_default()
+			})
+	}
+
+	/// decodes and caches the blob for `path` at `commit_id` if it
+	/// hasn't been decoded yet; a no-op if the blob isn't a recognized
+	/// image format (checked via its magic bytes)
+	fn update_image_preview(
+		&mut self,
+		commit_id: CommitId,
+		path: &str,
+	) -> Result<()> {
+		if self.image_previews.borrow().contains_key(&commit_id) {
+			return Ok(());
+		}
+
+		let bytes =
+			read_blob_at_commit(&self.repo_path.borrow(), commit_id, path)?;
+
+		if image::guess_format(&bytes).is_err() {
+			return Ok(());
+		}
+
+		if let Ok(image) = image::load_from_memory(&bytes) {
+			self.image_previews.borrow_mut().insert(
+				commit_id,
+				ImagePreview {
+					image,
+					last_render: std::cell::RefCell::new(None),
+				},
+			);
+		}
+
+		Ok(())
+	}
+
+	/// renders the cached preview for the selected commit as
+	/// half-block (`▀`) lines sized to `width`x`height` cells, scaling
+	/// only when the requested size changed since the last render
+	fn selected_image_preview_lines(
+		&self,
+		width: u32,
+		height: u32,
+	) -> Option<Vec<Spans<'static>>> {
+		if width == 0 || height == 0 {
+			return None;
+		}
+
+		let commit_id = self.selected_commit()?;
+		let previews = self.image_previews.borrow();
+		let preview = previews.get(&commit_id)?;
+
+		let mut last_render = preview.last_render.borrow_mut();
+
+		if let Some((cached_width, cached_height, lines)) =
+			last_render.as_ref()
+		{
+			if *cached_width == width && *cached_height == height {
+				return Some(lines.clone());
+			}
+		}
+
+		// two vertically-stacked pixels per cell via the half-block
+		// glyph, so we need twice the cell height in pixel rows
+		let resized = preview
+			.image
+			.resize(width, height * 2, FilterType::Triangle)
+			.to_rgba8();
+
+		let mut lines =
+			Vec::with_capacity(resized.height() as usize / 2 + 1);
+
+		for y in (0..resized.height()).step_by(2) {
+			let mut spans =
+				Vec::with_capacity(resized.width() as usize);
+
+			for x in 0..resized.width() {
+				let top = *resized.get_pixel(x, y);
+				let bottom = resized
+					.get_pixel_checked(x, y + 1)
+					.copied()
+					.unwrap_or(top);
+
+				spans.push(Span::styled(
+					"▀",
+					Style::default()
+						.fg(Color::Rgb(top[0], top[1], top[2]))
+						.bg(Color::Rgb(
+							bottom[0], bottom[1], bottom[2],
+						)),
+				));
+			}
+
+			lines.push(Spans::from(spans));
+		}
+
+		*last_render = Some((width, height, lines.clone()));
+
+		Some(lines)
+	}
+
+	fn draw_highlighted_diff<B: Backend>(
+		&self,
+		f: &mut Frame<B>,
+		area: Rect,
+	) -> bool {
+		let (path, diff) = match self.current_diff.as_ref() {
+			Some(current_diff) => current_diff,
+			None => return false,
+		};
+
+		if !has_known_syntax(path) {
+			return false;
+		}
+
+		let paragraph = Paragraph::new(highlighted_diff_text(path, diff))
+			.block(
+				Block::default()
+					.borders(Borders::ALL)
+					.title(Span::styled(
+						path.as_str(),
+						self.theme.title(self.diff.focused()),
+					))
+					.border_style(self.theme.block(self.diff.focused())),
+			);
+
+		f.render_widget(paragraph, area);
+
+		true
+	}
+
+	fn draw_image_preview<B: Backend>(
+		&self,
+		f: &mut Frame<B>,
+		area: Rect,
+		lines: Vec<Spans<'static>>,
+	) {
+		let paragraph = Paragraph::new(lines).block(
+			Block::default()
+				.borders(Borders::ALL)
+				.title(Span::styled(
+					"image preview",
+					self.theme.title(self.diff.focused()),
+				))
+				.border_style(self.theme.block(self.diff.focused())),
+		);
+
+		f.render_widget(paragraph, area);
+	}
+
+	fn selected_item_index(&self) -> Option<usize> {
 		let table_state = self.table_state.take();
+		let selected = table_state.selected();
+		self.table_state.set(table_state);
 
-		let commit_id = table_state.selected().and_then(|selected| {
+		selected.and_then(|row| self.visible_item_index(row))
+	}
+
+	fn selected_commit(&self) -> Option<CommitId> {
+		self.selected_item_index().and_then(|item_index| {
 			self.items
 				.iter()
-				.nth(selected)
+				.nth(item_index)
 				.as_ref()
 				.map(|entry| entry.id)
-		});
-
-		self.table_state.set(table_state);
-
-		commit_id
+		})
 	}
 
 	fn can_focus_diff(&self) -> bool {
@@ -246,46 +643,267 @@ impl FileRevlogComponent {
 		self.file_path.as_ref().map_or(
 			"<no history available>".into(),
 			|file_path| {
-				strings::file_log_title(&self.key_config, file_path)
+				let title = strings::file_log_title(
+					&self.key_config,
+					file_path,
+				);
+
+				let title = self.filter.as_ref().map_or_else(
+					|| title.clone(),
+					|filter| {
+						if filter.editing
+							|| !filter.query.is_empty()
+						{
+							format!(
+								"{} [find: {}]",
+								title, filter.query
+							)
+						} else {
+							title.clone()
+						}
+					},
+				);
+
+				match self
+					.selected_item_index()
+					.map(|item_index| self.path_for_index(item_index))
+				{
+					Some(original_path)
+						if original_path != file_path =>
+					{
+						format!(
+							"{} (originally {})",
+							title, original_path
+						)
+					}
+					_ => title,
+				}
 			},
 		)
 	}
 
+	/// index into `self.items` for a given row of the currently
+	/// displayed (possibly filtered) table
+	fn visible_item_index(&self, row: usize) -> Option<usize> {
+		match &self.filter {
+			Some(filter) if !filter.query.is_empty() => filter
+				.matches
+				.get(row)
+				.map(|filter_match| filter_match.item_index),
+			_ => Some(row),
+		}
+	}
+
+	/// (re-)compute the fuzzy matches for the active filter query
+	/// against the currently loaded commits
+	fn apply_filter(&mut self) {
+		let query = match &self.filter {
+			Some(filter) => filter.query.clone(),
+			None => return,
+		};
+
+		let mut matches = Vec::new();
+
+		if !query.is_empty() {
+			for (item_index, entry) in
+				self.items.iter().enumerate()
+			{
+				let hash =
+					fuzzy_match(&entry.hash_short, &query);
+				let author =
+					fuzzy_match(&entry.author, &query);
+				let msg = fuzzy_match(&entry.msg, &query);
+
+				let score = hash.as_ref().map_or(0, |m| m.0)
+					+ author.as_ref().map_or(0, |m| m.0)
+					+ msg.as_ref().map_or(0, |m| m.0);
+
+				if score <= 0 {
+					continue;
+				}
+
+				matches.push(FilterMatch {
+					item_index,
+					score,
+					hash_indices: hash
+						.map_or_else(Vec::new, |m| m.1),
+					author_indices: author
+						.map_or_else(Vec::new, |m| m.1),
+					msg_indices: msg
+						.map_or_else(Vec::new, |m| m.1),
+				});
+			}
+
+			matches.sort_by(|a, b| b.score.cmp(&a.score));
+		}
+
+		if let Some(filter) = self.filter.as_mut() {
+			filter.matches = matches;
+		}
+
+		self.set_selection(0);
+	}
+
+	fn set_selection(&mut self, selection: usize) {
+		let mut table_state = self.table_state.take();
+		table_state.select(Some(selection));
+		self.table_state.set(table_state);
+
+		self.queue.push(InternalEvent::Update(NeedsUpdate::DIFF));
+	}
+
+	fn start_filter(&mut self) {
+		let mut filter = self.filter.take().unwrap_or_default();
+		filter.editing = true;
+		self.filter = Some(filter);
+	}
+
+	fn stop_filter(&mut self, keep: bool) {
+		if keep {
+			if let Some(filter) = self.filter.as_mut() {
+				filter.editing = false;
+			}
+		} else {
+			self.filter = None;
+		}
+
+		self.set_selection(0);
+	}
+
+	fn toggle_follow_renames(&mut self) {
+		self.follow_renames = !self.follow_renames;
+
+		if self.follow_renames {
+			self.renames_resolved_until = 0;
+			self.resolve_renames();
+		} else if let Some(file_path) = &self.file_path {
+			// invalidate any thread still resolving renames from while
+			// this was enabled, so it cannot extend the history below
+			// with stale segments once it completes
+			self.renames_epoch
+				.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+			if let Ok(mut history) = self.path_history.lock() {
+				*history = vec![PathSegment {
+					start: 0,
+					path: file_path.clone(),
+				}];
+			}
+			self.renames_resolved_until = 0;
+		}
+
+		self.queue.push(InternalEvent::Update(NeedsUpdate::DIFF));
+	}
+
+	fn handle_filter_key(
+		&mut self,
+		key: KeyEvent,
+	) -> Result<EventState> {
+		match key.code {
+			KeyCode::Esc => self.stop_filter(false),
+			KeyCode::Enter => self.stop_filter(true),
+			KeyCode::Backspace => {
+				if let Some(filter) = self.filter.as_mut() {
+					filter.query.pop();
+				}
+				self.apply_filter();
+			}
+			KeyCode::Char(c) => {
+				if let Some(filter) = self.filter.as_mut() {
+					filter.query.push(c);
+				}
+				self.apply_filter();
+			}
+			_ => return Ok(EventState::NotConsumed),
+		}
+
+		Ok(EventState::Consumed)
+	}
+
 	fn get_rows(&self, now: DateTime<Local>) -> Vec<Row> {
-		self.items
-			.iter()
-			.map(|entry| {
-				let spans = Spans::from(vec![
-					Span::styled(
-						entry.hash_short.to_string(),
-						self.theme.commit_hash(false),
-					),
-					Span::raw(" "),
-					Span::styled(
-						entry.time_to_string(now),
-						self.theme.commit_time(false),
-					),
-					Span::raw(" "),
-					Span::styled(
-						entry.author.to_string(),
-						self.theme.commit_author(false),
-					),
-				]);
+		match &self.filter {
+			Some(filter) if !filter.query.is_empty() => filter
+				.matches
+				.iter()
+				.filter_map(|filter_match| {
+					self.items
+						.iter()
+						.nth(filter_match.item_index)
+						.map(|entry| {
+							self.render_row(
+								entry,
+								now,
+								Some(filter_match),
+							)
+						})
+				})
+				.collect(),
+			_ => self
+				.items
+				.iter()
+				.map(|entry| self.render_row(entry, now, None))
+				.collect(),
+		}
+	}
 
-				let mut text = Text::from(spans);
-				text.extend(Text::raw(entry.msg.to_string()));
+	fn render_row(
+		&self,
+		entry: &LogEntry,
+		now: DateTime<Local>,
+		filter_match: Option<&FilterMatch>,
+	) -> Row {
+		let highlight = self.theme.text(true, false).patch(
+			Style::default().add_modifier(Modifier::UNDERLINED),
+		);
 
-				let cells = vec![Cell::from(""), Cell::from(text)];
+		let empty: Vec<usize> = Vec::new();
+		let hash_indices =
+			filter_match.map_or(&empty, |m| &m.hash_indices);
+		let author_indices =
+			filter_match.map_or(&empty, |m| &m.author_indices);
+		let msg_indices =
+			filter_match.map_or(&empty, |m| &m.msg_indices);
+
+		let mut first_line = highlighted_spans(
+			&entry.hash_short,
+			hash_indices,
+			self.theme.commit_hash(false),
+			highlight,
+		);
+		first_line.push(Span::raw(" "));
+		first_line.push(Span::styled(
+			entry.time_to_string(now),
+			self.theme.commit_time(false),
+		));
+		first_line.push(Span::raw(" "));
+		first_line.extend(highlighted_spans(
+			&entry.author,
+			author_indices,
+			self.theme.commit_author(false),
+			highlight,
+		));
 
-				Row::new(cells).height(2)
-			})
-			.collect()
+		let mut text = Text::from(Spans::from(first_line));
+		text.extend(Text::from(Spans::from(highlighted_spans(
+			&entry.msg,
+			msg_indices,
+			Style::default(),
+			highlight,
+		))));
+
+		let cells = vec![Cell::from(""), Cell::from(text)];
+
+		Row::new(cells).height(2)
 	}
 
 	fn get_max_selection(&mut self) -> usize {
-		self.git_log.as_mut().map_or(0, |log| {
-			log.count().unwrap_or(0).saturating_sub(1)
-		})
+		match &self.filter {
+			Some(filter) if !filter.query.is_empty() => {
+				filter.matches.len().saturating_sub(1)
+			}
+			_ => self.git_log.as_mut().map_or(0, |log| {
+				log.count().unwrap_or(0).saturating_sub(1)
+			}),
+		}
 	}
 
 	fn move_selection(&mut self, scroll_type: ScrollType) -> bool {
@@ -396,7 +1014,24 @@ impl DrawableComponent for FileRevlogComponent {
 			f.render_widget(Clear, area);
 
 			self.draw_revlog(f, chunks[0]);
-			self.diff.draw(f, chunks[1])?;
+
+			let preview_lines = self.selected_image_preview_lines(
+				chunks[1].width.saturating_sub(2).into(),
+				chunks[1].height.saturating_sub(2).into(),
+			);
+
+			if let Some(lines) = preview_lines {
+				self.draw_image_preview(f, chunks[1], lines);
+			} else if self.diff.focused()
+				|| !self.draw_highlighted_diff(f, chunks[1])
+			{
+				// `DiffComponent` owns scroll state once the pane is
+				// focused; the highlighted paragraph has no
+				// scroll offset of its own, so defer to the real
+				// diff renderer whenever the user can actually
+				// scroll it
+				self.diff.draw(f, chunks[1])?;
+			}
 		}
 
 		Ok(())
@@ -416,6 +1051,14 @@ impl Component for FileRevlogComponent {
 			}
 
 			if let Event::Key(key) = event {
+				if self
+					.filter
+					.as_ref()
+					.map_or(false, |filter| filter.editing)
+				{
+					return self.handle_filter_key(key);
+				}
+
 				if key == self.key_config.keys.exit_popup {
 					self.hide();
 
@@ -446,6 +1089,19 @@ impl Component for FileRevlogComponent {
 							Ok(EventState::Consumed)
 						},
 					);
+				} else if key == self.key_config.keys.file_find_commit
+					&& !self.diff.focused()
+				{
+					self.start_filter();
+
+					return Ok(EventState::Consumed);
+				} else if key
+					== self.key_config.keys.file_follow_renames
+					&& !self.diff.focused()
+				{
+					self.toggle_follow_renames();
+
+					return Ok(EventState::Consumed);
 				} else if key == self.key_config.keys.move_up {
 					self.move_selection(ScrollType::Up)
 				} else if key == self.key_config.keys.move_down {
@@ -497,7 +1153,6 @@ impl Component for FileRevlogComponent {
 				)
 				.order(1),
 			);
-
 			out.push(CommandInfo::new(
 				strings::commands::diff_focus_right(&self.key_config),
 				self.can_focus_diff(),
@@ -508,6 +1163,19 @@ impl Component for FileRevlogComponent {
 				true,
 				self.diff.focused(),
 			));
+			out.push(CommandInfo::new(
+				strings::commands::file_find_commit(&self.key_config),
+				true,
+				!self.diff.focused(),
+			));
+			out.push(CommandInfo::new(
+				strings::commands::file_follow_renames(
+					&self.key_config,
+					self.follow_renames,
+				),
+				true,
+				!self.diff.focused(),
+			));
 		}
 
 		visibility_blocking(self)
@@ -527,3 +1195,371 @@ impl Component for FileRevlogComponent {
 		Ok(())
 	}
 }
+
+static SYNTAX_SET: Lazy<SyntaxSet> =
+	Lazy::new(SyntaxSet::load_defaults_newlines);
+static DIFF_THEME: Lazy<Theme> = Lazy::new(|| {
+	let mut theme_set = ThemeSet::load_defaults();
+	theme_set
+		.themes
+		.remove("base16-ocean.dark")
+		.unwrap_or_default()
+});
+
+/// per-file syntax highlighter for the revlog diff pane, fed
+/// marker-stripped logical lines one hunk line at a time
+struct HunkHighlighter {
+	highlighter: Option<HighlightLines<'static>>,
+}
+
+impl HunkHighlighter {
+	fn new(file_path: &str) -> Self {
+		let syntax = std::path::Path::new(file_path)
+			.extension()
+			.and_then(std::ffi::OsStr::to_str)
+			.and_then(|ext| SYNTAX_SET.find_syntax_by_extension(ext));
+
+		Self {
+			highlighter: syntax.map(|syntax| {
+				HighlightLines::new(syntax, &DIFF_THEME)
+			}),
+		}
+	}
+
+	fn highlight_line(&mut self, line: &str) -> Vec<Span<'static>> {
+		let highlighter = match self.highlighter.as_mut() {
+			Some(highlighter) => highlighter,
+			None => return vec![Span::raw(line.to_string())],
+		};
+
+		let mut owned_line = String::with_capacity(line.len() + 1);
+		owned_line.push_str(line);
+		owned_line.push('\n');
+
+		highlighter
+			.highlight_line(&owned_line, &SYNTAX_SET)
+			.map(|spans| {
+				spans
+					.into_iter()
+					.map(|(style, text)| {
+						Span::styled(
+							text.trim_end_matches('\n').to_string(),
+							syntect_style_to_tui(style),
+						)
+					})
+					.collect()
+			})
+			.unwrap_or_else(|_| vec![Span::raw(line.to_string())])
+	}
+}
+
+fn syntect_style_to_tui(style: SyntectStyle) -> Style {
+	Style::default().fg(Color::Rgb(
+		style.foreground.r,
+		style.foreground.g,
+		style.foreground.b,
+	))
+}
+
+/// background tint patched on top of the syntax-highlighted foreground
+/// for added/removed lines; diff coloring is otherwise owned by
+/// `DiffComponent`, which does not draw unfocused known-syntax diffs, so
+/// the tint is applied directly rather than through `SharedTheme`
+fn diff_line_background(line_type: DiffLineType) -> Option<Style> {
+	match line_type {
+		DiffLineType::Add => {
+			Some(Style::default().bg(Color::Rgb(0, 40, 0)))
+		}
+		DiffLineType::Delete => {
+			Some(Style::default().bg(Color::Rgb(40, 0, 0)))
+		}
+		_ => None,
+	}
+}
+
+/// whether `path`'s extension has a known syntect syntax, i.e. whether
+/// `highlighted_diff_text` has anything to highlight for it
+fn has_known_syntax(path: &str) -> bool {
+	std::path::Path::new(path)
+		.extension()
+		.and_then(std::ffi::OsStr::to_str)
+		.and_then(|ext| SYNTAX_SET.find_syntax_by_extension(ext))
+		.is_some()
+}
+
+/// renders `diff`'s hunks with per-line syntax highlighting, stripping
+/// the leading `+`/`-`/` ` marker before feeding each logical line to
+/// the highlighter and patching the add/removed background on top.
+/// hunks are discontiguous, so the highlighter is reset at the start
+/// of each one rather than carried over from the previous hunk
+fn highlighted_diff_text<'a>(path: &str, diff: &FileDiff) -> Text<'a> {
+	let mut lines = Vec::new();
+
+	for hunk in &diff.hunks {
+		let mut highlighter = HunkHighlighter::new(path);
+
+		for line in &hunk.lines {
+			let content = line.content.trim_end_matches('\n');
+			let marker = match line.line_type {
+				DiffLineType::Add => "+",
+				DiffLineType::Delete => "-",
+				_ => " ",
+			};
+
+			let mut spans = vec![Span::raw(marker)];
+			spans.extend(highlighter.highlight_line(content));
+
+			if let Some(bg) = diff_line_background(line.line_type) {
+				spans = spans
+					.into_iter()
+					.map(|span| {
+						Span::styled(span.content, span.style.patch(bg))
+					})
+					.collect();
+			}
+
+			lines.push(Spans::from(spans));
+		}
+	}
+
+	Text::from(lines)
+}
+
+/// looks up the commit's diff against its first parent for a rename
+/// whose new side is `path`, returning the old side if found. Runs off
+/// the UI thread (see `resolve_renames`) since it walks the tree diff
+/// via libgit2 directly.
+fn find_rename_source(
+	repo_path: &RepoPath,
+	commit_id: CommitId,
+	path: &str,
+) -> Result<Option<String>> {
+	let repo = open_repo(repo_path)?;
+	let commit = repo.find_commit(commit_id.get_oid())?;
+	let tree = commit.tree()?;
+	let parent_tree = commit
+		.parent(0)
+		.ok()
+		.map(|parent| parent.tree())
+		.transpose()?;
+
+	let mut diff_opts = Git2DiffOptions::new();
+	let mut diff = repo.diff_tree_to_tree(
+		parent_tree.as_ref(),
+		Some(&tree),
+		Some(&mut diff_opts),
+	)?;
+
+	let mut find_opts = git2::DiffFindOptions::new();
+	find_opts.renames(true);
+	diff.find_similar(Some(&mut find_opts))?;
+
+	for delta in diff.deltas() {
+		if delta.status() != git2::Delta::Renamed {
+			continue;
+		}
+
+		let new_path = delta.new_file().path().and_then(|p| p.to_str());
+
+		if new_path == Some(path) {
+			return Ok(delta
+				.old_file()
+				.path()
+				.and_then(|p| p.to_str())
+				.map(String::from));
+		}
+	}
+
+	Ok(None)
+}
+
+/// reads the raw bytes of the blob at `path` as of `commit_id`
+fn read_blob_at_commit(
+	repo_path: &RepoPath,
+	commit_id: CommitId,
+	path: &str,
+) -> Result<Vec<u8>> {
+	let repo = open_repo(repo_path)?;
+	let commit = repo.find_commit(commit_id.get_oid())?;
+	let tree = commit.tree()?;
+	let entry = tree.get_path(std::path::Path::new(path))?;
+	let blob = entry.to_object(&repo)?.peel_to_blob()?;
+
+	Ok(blob.content().to_vec())
+}
+
+/// whether `path`'s extension suggests a binary image format; the
+/// actual decode still validates this against the blob's magic bytes
+/// via `image::guess_format`
+fn is_image_path(path: &str) -> bool {
+	std::path::Path::new(path)
+		.extension()
+		.and_then(std::ffi::OsStr::to_str)
+		.map_or(false, |extension| {
+			matches!(
+				extension.to_ascii_lowercase().as_str(),
+				"png" | "jpg" | "jpeg" | "gif" | "webp"
+			)
+		})
+}
+
+/// build the spans for a field of text, switching between `base` and
+/// `highlight` style at the boundaries of the fuzzy-matched character
+/// indices
+fn highlighted_spans<'a>(
+	text: &'a str,
+	indices: &[usize],
+	base: Style,
+	highlight: Style,
+) -> Vec<Span<'a>> {
+	if indices.is_empty() {
+		return vec![Span::styled(text, base)];
+	}
+
+	let mut spans = Vec::new();
+	let mut buf = String::new();
+	let mut in_match = false;
+
+	for (i, c) in text.chars().enumerate() {
+		let matched = indices.contains(&i);
+
+		if matched != in_match && !buf.is_empty() {
+			spans.push(Span::styled(
+				std::mem::take(&mut buf),
+				if in_match { highlight } else { base },
+			));
+		}
+
+		in_match = matched;
+		buf.push(c);
+	}
+
+	if !buf.is_empty() {
+		spans.push(Span::styled(
+			buf,
+			if in_match { highlight } else { base },
+		));
+	}
+
+	spans
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Move {
+	None,
+	Diagonal,
+	Left,
+}
+
+/// fuzzy subsequence match of `needle` inside `haystack`, Smith-Waterman
+/// style: characters score points, consecutive matches and matches at
+/// word boundaries (after a space/`_`/`-`/`/` or a camelCase hump) score
+/// a bonus, and gaps between matches are penalized. Returns `None` when
+/// `needle` cannot be matched as a subsequence of `haystack` or when the
+/// best match scores zero or less, otherwise the total score and the
+/// matched character indices (by `char` offset) into `haystack`.
+fn fuzzy_match(
+	haystack: &str,
+	needle: &str,
+) -> Option<(i64, Vec<usize>)> {
+	if needle.is_empty() || haystack.is_empty() {
+		return None;
+	}
+
+	const MATCH_BONUS: i64 = 16;
+	const CONSECUTIVE_BONUS: i64 = 8;
+	const BOUNDARY_BONUS: i64 = 12;
+	const GAP_PENALTY: i64 = 1;
+
+	let haystack: Vec<char> = haystack.chars().collect();
+	let needle: Vec<char> = needle.chars().collect();
+
+	let rows = needle.len() + 1;
+	let cols = haystack.len() + 1;
+
+	let mut score = vec![0i64; rows * cols];
+	let mut trace = vec![Move::None; rows * cols];
+
+	// a needle character can only be taken once every earlier one has
+	// been, so no row past the first may start from an empty haystack
+	// prefix
+	for i in 1..rows {
+		score[i * cols] = i64::MIN / 2;
+	}
+
+	for i in 1..rows {
+		for j in 1..cols {
+			let idx = i * cols + j;
+
+			let skip = score[idx - 1] - GAP_PENALTY;
+
+			let take = needle[i - 1]
+				.to_ascii_lowercase()
+				.eq(&haystack[j - 1].to_ascii_lowercase())
+				.then(|| {
+					let boundary = j == 1
+						|| matches!(
+							haystack[j - 2],
+							' ' | '_' | '-' | '/'
+						) || (haystack[j - 2].is_lowercase()
+						&& haystack[j - 1].is_uppercase());
+					let consecutive = i > 1
+						&& trace[(i - 1) * cols + (j - 1)]
+							== Move::Diagonal;
+
+					let mut bonus = MATCH_BONUS;
+					if boundary {
+						bonus += BOUNDARY_BONUS;
+					}
+					if consecutive {
+						bonus += CONSECUTIVE_BONUS;
+					}
+
+					score[(i - 1) * cols + (j - 1)] + bonus
+				});
+
+			match take {
+				Some(take) if take >= skip => {
+					score[idx] = take;
+					trace[idx] = Move::Diagonal;
+				}
+				_ => {
+					score[idx] = skip;
+					trace[idx] = Move::Left;
+				}
+			}
+		}
+	}
+
+	// the needle is fully consumed once we reach the last row, but the
+	// haystack may still have unmatched characters after the match -
+	// scan the whole row for the best ending column instead of reading
+	// the bottom-right corner, so trailing gaps aren't penalized
+	let last_row = (rows - 1) * cols;
+	let (best_j, total) = (0..cols)
+		.map(|j| (j, score[last_row + j]))
+		.max_by_key(|&(_, score)| score)
+		.unwrap_or((cols - 1, 0));
+
+	if total <= 0 {
+		return None;
+	}
+
+	let mut indices = Vec::with_capacity(needle.len());
+	let (mut i, mut j) = (rows - 1, best_j);
+
+	while i > 0 && j > 0 {
+		match trace[i * cols + j] {
+			Move::Diagonal => {
+				indices.push(j - 1);
+				i -= 1;
+				j -= 1;
+			}
+			_ => j -= 1,
+		}
+	}
+
+	indices.reverse();
+
+	Some((total, indices))
+}